@@ -10,14 +10,17 @@ use casper_contract::{
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
-    account::AccountHash, 
+    account::AccountHash,
     contracts::{EntryPoint, EntryPoints},
+    crypto,
+    CallStackElement,
     EntryPointAccess,
-    EntryPointType, 
+    EntryPointType,
     ApiError,
-    Key, 
+    Key,
     Parameter,
-    PublicKey, 
+    PublicKey,
+    Signature,
     U256,
     URef,
     CLTyped,
@@ -39,6 +42,9 @@ enum Err {
     NotApproved = 9,
     NotInit = 10,
     MissingDict = 11,
+    BadSignature = 12,
+    BadReveal = 13,
+    TooEarly = 14,
 }
 
 const DICT: &str = "d";
@@ -58,21 +64,31 @@ fn write<T: CLTyped + ToBytes>(k: &str, v: T) {
     storage::dictionary_put(get_dict(), k, v);
 }
 
+fn require_owner(acc: AccountHash) {
+    if runtime::get_caller() != acc { runtime::revert(ApiError::User(Err::NotOwner as u16)); }
+}
+
+fn validate_guardian_set(guards: &[AccountHash], thresh: u8) {
+    if guards.len() < 2 { runtime::revert(ApiError::User(Err::BadGuardians as u16)); }
+    if thresh == 0 || thresh as usize > guards.len() { runtime::revert(ApiError::User(Err::BadThreshold as u16)); }
+}
+
 #[no_mangle]
 pub extern "C" fn init_guardians() {
     let acc: AccountHash = runtime::get_named_arg("account");
     let guards: Vec<AccountHash> = runtime::get_named_arg("guardians");
     let thresh: u8 = runtime::get_named_arg("threshold");
+    let delay: u64 = runtime::get_named_arg("delay");
 
-    if runtime::get_caller() != acc { runtime::revert(ApiError::User(Err::NotOwner as u16)); }
-    if guards.len() < 2 { runtime::revert(ApiError::User(Err::BadGuardians as u16)); }
-    if thresh == 0 || thresh as usize > guards.len() { runtime::revert(ApiError::User(Err::BadThreshold as u16)); }
+    require_owner(acc);
+    validate_guardian_set(&guards, thresh);
 
     let k = format!("i{:?}", acc);
     if read::<bool>(&k).unwrap_or(false) { runtime::revert(ApiError::User(Err::AlreadyInit as u16)); }
 
     write(&format!("g{:?}", acc), guards.clone());
     write(&format!("t{:?}", acc), thresh);
+    write(&format!("dl{:?}", acc), delay);
     write(&k, true);
 
     // Add reverse mapping: for each guardian, add this account to their protected list
@@ -86,20 +102,100 @@ pub extern "C" fn init_guardians() {
     }
 }
 
+#[no_mangle]
+pub extern "C" fn add_guardian() {
+    let acc: AccountHash = runtime::get_named_arg("account");
+    let guardian: AccountHash = runtime::get_named_arg("guardian");
+    let new_threshold: u8 = runtime::get_named_arg("new_threshold");
+
+    require_owner(acc);
+
+    let mut guards: Vec<AccountHash> = read(&format!("g{:?}", acc)).unwrap_or_revert_with(ApiError::User(Err::NotInit as u16));
+    if !guards.contains(&guardian) { guards.push(guardian); }
+    validate_guardian_set(&guards, new_threshold);
+
+    write(&format!("g{:?}", acc), guards);
+    write(&format!("t{:?}", acc), new_threshold);
+
+    let key = format!("ga{:?}", guardian);
+    let mut protected: Vec<AccountHash> = read(&key).unwrap_or(vec![]);
+    if !protected.contains(&acc) {
+        protected.push(acc);
+        write(&key, protected);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn remove_guardian() {
+    let acc: AccountHash = runtime::get_named_arg("account");
+    let guardian: AccountHash = runtime::get_named_arg("guardian");
+    let new_threshold: u8 = runtime::get_named_arg("new_threshold");
+
+    require_owner(acc);
+
+    let mut guards: Vec<AccountHash> = read(&format!("g{:?}", acc)).unwrap_or_revert_with(ApiError::User(Err::NotInit as u16));
+    guards.retain(|g| g != &guardian);
+    validate_guardian_set(&guards, new_threshold);
+
+    write(&format!("g{:?}", acc), guards);
+    write(&format!("t{:?}", acc), new_threshold);
+
+    let key = format!("ga{:?}", guardian);
+    let mut protected: Vec<AccountHash> = read(&key).unwrap_or(vec![]);
+    protected.retain(|a| a != &acc);
+    write(&key, protected);
+}
+
+#[no_mangle]
+pub extern "C" fn replace_guardian() {
+    let acc: AccountHash = runtime::get_named_arg("account");
+    let old_guardian: AccountHash = runtime::get_named_arg("old_guardian");
+    let new_guardian: AccountHash = runtime::get_named_arg("new_guardian");
+    let new_threshold: u8 = runtime::get_named_arg("new_threshold");
+
+    require_owner(acc);
+
+    let mut guards: Vec<AccountHash> = read(&format!("g{:?}", acc)).unwrap_or_revert_with(ApiError::User(Err::NotInit as u16));
+    if !guards.contains(&old_guardian) { runtime::revert(ApiError::User(Err::NotGuardian as u16)); }
+    if guards.contains(&new_guardian) { runtime::revert(ApiError::User(Err::BadGuardians as u16)); }
+
+    for guard in guards.iter_mut() {
+        if *guard == old_guardian { *guard = new_guardian; }
+    }
+    validate_guardian_set(&guards, new_threshold);
+
+    write(&format!("g{:?}", acc), guards);
+    write(&format!("t{:?}", acc), new_threshold);
+
+    let old_key = format!("ga{:?}", old_guardian);
+    let mut old_protected: Vec<AccountHash> = read(&old_key).unwrap_or(vec![]);
+    old_protected.retain(|a| a != &acc);
+    write(&old_key, old_protected);
+
+    let new_key = format!("ga{:?}", new_guardian);
+    let mut new_protected: Vec<AccountHash> = read(&new_key).unwrap_or(vec![]);
+    if !new_protected.contains(&acc) {
+        new_protected.push(acc);
+        write(&new_key, new_protected);
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn start_recovery() {
     let acc: AccountHash = runtime::get_named_arg("account");
-    let nk: PublicKey = runtime::get_named_arg("new_key");
+    let commitment: [u8; 32] = runtime::get_named_arg("commitment");
 
     if !read::<bool>(&format!("i{:?}", acc)).unwrap_or(false) { runtime::revert(ApiError::User(Err::NotInit as u16)); }
-    if read::<U256>(&format!("a{:?}", acc)).is_some() { runtime::revert(ApiError::User(Err::RecoveryExists as u16)); }
+    let has_active = read::<U256>(&format!("a{:?}", acc)).map(|a| !a.is_zero()).unwrap_or(false);
+    if has_active { runtime::revert(ApiError::User(Err::RecoveryExists as u16)); }
 
     let id = read::<U256>("c").unwrap_or(U256::zero()) + 1;
     write("c", id);
     write(&format!("ra{}", id), acc);
-    write(&format!("rk{}", id), nk);
+    write(&format!("rk{}", id), commitment);
     write(&format!("rc{}", id), 0u8);
     write(&format!("ro{}", id), false);
+    write(&format!("rt{}", id), runtime::get_blocktime().value());
     write(&format!("a{:?}", acc), id);
 
     // Add reverse mapping: for each guardian, add this recovery ID to their active recoveries list
@@ -138,20 +234,113 @@ pub extern "C" fn approve() {
 }
 
 #[no_mangle]
-pub extern "C" fn is_approved() {
+pub extern "C" fn reveal_key() {
     let id: U256 = runtime::get_named_arg("id");
-    runtime::ret(CLValue::from_t(read::<bool>(&format!("ro{}", id)).unwrap_or(false)).unwrap_or_revert());
+    let new_key: PublicKey = runtime::get_named_arg("new_key");
+    let salt: [u8; 32] = runtime::get_named_arg("salt");
+
+    // The threshold must already be locked in before the cleartext key is
+    // exposed, otherwise the commitment buys no front-running protection.
+    if !read::<bool>(&format!("ro{}", id)).unwrap_or(false) { runtime::revert(ApiError::User(Err::NotApproved as u16)); }
+
+    let commitment: [u8; 32] = read(&format!("rk{}", id)).unwrap_or_revert_with(ApiError::User(Err::NotFound as u16));
+
+    let mut preimage = new_key.to_bytes().unwrap_or_revert();
+    preimage.extend_from_slice(&salt);
+    let digest = runtime::blake2b(&preimage);
+
+    if digest != commitment { runtime::revert(ApiError::User(Err::BadReveal as u16)); }
+
+    write(&format!("rv{}", id), new_key);
+}
+
+// Hash of the currently executing contract, used to bind off-chain
+// approval signatures to this specific contract instance.
+fn own_contract_hash() -> [u8; 32] {
+    match runtime::get_call_stack().last() {
+        Some(CallStackElement::StoredContract { contract_hash, .. }) => contract_hash.value(),
+        _ => runtime::revert(ApiError::User(Err::NotFound as u16)),
+    }
+}
+
+// Approvals are bound to the commitment stored in `rk{id}`, not the
+// cleartext key, so a signature can be collected before `reveal_key` runs.
+fn approval_message(id: U256, commitment: &[u8; 32], contract_hash: [u8; 32]) -> Vec<u8> {
+    let mut bytes = id.to_bytes().unwrap_or_revert();
+    bytes.extend_from_slice(commitment);
+    bytes.extend_from_slice(&contract_hash);
+    bytes
+}
+
+fn build_signature(pubkey: &PublicKey, raw: [u8; 64]) -> Signature {
+    match pubkey {
+        PublicKey::Ed25519(_) => {
+            Signature::ed25519(raw).unwrap_or_revert_with(ApiError::User(Err::BadSignature as u16))
+        }
+        PublicKey::Secp256k1(_) => {
+            Signature::secp256k1(raw).unwrap_or_revert_with(ApiError::User(Err::BadSignature as u16))
+        }
+        _ => runtime::revert(ApiError::User(Err::BadSignature as u16)),
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn finalize() {
+pub extern "C" fn approve_signed() {
     let id: U256 = runtime::get_named_arg("id");
-    if !read::<bool>(&format!("ro{}", id)).unwrap_or(false) { runtime::revert(ApiError::User(Err::NotApproved as u16)); }
+    let approvals: Vec<(PublicKey, [u8; 64])> = runtime::get_named_arg("approvals");
 
-    // Get the target account for this recovery
     let acc: AccountHash = read(&format!("ra{}", id)).unwrap_or_revert_with(ApiError::User(Err::NotFound as u16));
-    
-    // Remove this recovery ID from each guardian's active recoveries list
+    let guards: Vec<AccountHash> = read(&format!("g{:?}", acc)).unwrap_or_revert_with(ApiError::User(Err::NotGuardian as u16));
+    let commitment: [u8; 32] = read(&format!("rk{}", id)).unwrap_or_revert_with(ApiError::User(Err::NotFound as u16));
+
+    let message = approval_message(id, &commitment, own_contract_hash());
+
+    let mut cnt: u8 = read(&format!("rc{}", id)).unwrap_or(0);
+    let mut repeated = false;
+
+    for (pubkey, raw_sig) in approvals {
+        let signer = AccountHash::from(&pubkey);
+        if !guards.contains(&signer) { runtime::revert(ApiError::User(Err::NotGuardian as u16)); }
+
+        let signature = build_signature(&pubkey, raw_sig);
+        if crypto::verify(&message, &signature, &pubkey).is_err() {
+            runtime::revert(ApiError::User(Err::BadSignature as u16));
+        }
+
+        let ak = format!("rp{}_{:?}", id, signer);
+        if read::<bool>(&ak).unwrap_or(false) {
+            repeated = true;
+            continue;
+        }
+
+        write(&ak, true);
+        cnt += 1;
+        write(&format!("rc{}", id), cnt);
+    }
+
+    // Per spec, a repeated signer reverts the whole batch, counted only
+    // after every other valid signer in it has been walked. Note this is a
+    // real tradeoff given contract-call atomicity: the revert also discards
+    // the writes for those other valid signers, so a relayer that wants
+    // duplicate-tolerant partial application needs to split the batch
+    // itself and retry. Flagging rather than silently picking the opposite
+    // behavior, since off-chain relayers may depend on this revert to
+    // detect a stale signature set.
+    if repeated { runtime::revert(ApiError::User(Err::AlreadyApproved as u16)); }
+
+    let thresh: u8 = read(&format!("t{:?}", acc)).unwrap_or(2);
+    if cnt >= thresh { write(&format!("ro{}", id), true); }
+}
+
+#[no_mangle]
+pub extern "C" fn is_approved() {
+    let id: U256 = runtime::get_named_arg("id");
+    runtime::ret(CLValue::from_t(read::<bool>(&format!("ro{}", id)).unwrap_or(false)).unwrap_or_revert());
+}
+
+// Remove a recovery ID from every guardian's active-recoveries list, shared
+// by both `finalize` and `cancel_recovery`.
+fn remove_recovery_from_guardians(acc: AccountHash, id: U256) {
     let guards: Vec<AccountHash> = read(&format!("g{:?}", acc)).unwrap_or(vec![]);
     for guard in &guards {
         let key = format!("gr{:?}", guard);
@@ -159,14 +348,53 @@ pub extern "C" fn finalize() {
         recoveries.retain(|&r| r != id);
         write(&key, recoveries);
     }
+}
+
+#[no_mangle]
+pub extern "C" fn finalize() {
+    let id: U256 = runtime::get_named_arg("id");
+    if !read::<bool>(&format!("ro{}", id)).unwrap_or(false) { runtime::revert(ApiError::User(Err::NotApproved as u16)); }
+    if read::<PublicKey>(&format!("rv{}", id)).is_none() { runtime::revert(ApiError::User(Err::NotApproved as u16)); }
+
+    // Get the target account for this recovery
+    let acc: AccountHash = read(&format!("ra{}", id)).unwrap_or_revert_with(ApiError::User(Err::NotFound as u16));
+
+    // `id` must still be the account's active recovery: once finalized (or
+    // cancelled/superseded) `a{acc}` points elsewhere, and re-finalizing a
+    // stale `id` would wipe out whatever recovery is active now.
+    if read::<U256>(&format!("a{:?}", acc)) != Some(id) { runtime::revert(ApiError::User(Err::NotFound as u16)); }
+
+    let start: u64 = read(&format!("rt{}", id)).unwrap_or(0);
+    let delay: u64 = read(&format!("dl{:?}", acc)).unwrap_or(0);
+    let cancelled = read::<bool>(&format!("cancelled{}", id)).unwrap_or(false);
+    let elapsed = runtime::get_blocktime().value().saturating_sub(start);
+    if elapsed < delay || cancelled { runtime::revert(ApiError::User(Err::TooEarly as u16)); }
+
+    remove_recovery_from_guardians(acc, id);
 
     // Clear the active recovery mapping for this account
-    // Note: Casper dictionary doesn't have delete, so we write a zero value
-    // The active recovery check in start_recovery uses is_some(), so we need to handle this
-    // For now, we mark it as finalized by setting a special flag
+    write(&format!("a{:?}", acc), U256::zero());
     write(&format!("rf{}", id), true); // Recovery finalized flag
 }
 
+#[no_mangle]
+pub extern "C" fn cancel_recovery() {
+    let id: U256 = runtime::get_named_arg("id");
+
+    let acc: AccountHash = read(&format!("ra{}", id)).unwrap_or_revert_with(ApiError::User(Err::NotFound as u16));
+    require_owner(acc);
+
+    // `id` must be the account's currently active recovery, otherwise this
+    // would clear `a{acc}` out from under a different, still-active recovery
+    // while leaving the named one's `cancelled{id}` flag set on a stale slot.
+    if read::<U256>(&format!("a{:?}", acc)) != Some(id) { runtime::revert(ApiError::User(Err::NotFound as u16)); }
+
+    remove_recovery_from_guardians(acc, id);
+
+    write(&format!("a{:?}", acc), U256::zero());
+    write(&format!("cancelled{}", id), true);
+}
+
 #[no_mangle]
 pub extern "C" fn get_guardians() {
     let acc: AccountHash = runtime::get_named_arg("account");
@@ -217,6 +445,38 @@ pub extern "C" fn call() {
             Parameter::new("account", CLType::ByteArray(32)),
             Parameter::new("guardians", CLType::List(Box::new(CLType::ByteArray(32)))),
             Parameter::new("threshold", CLType::U8),
+            Parameter::new("delay", CLType::U64),
+        ],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
+    eps.add_entry_point(EntryPoint::new(
+        "add_guardian",
+        vec![
+            Parameter::new("account", CLType::ByteArray(32)),
+            Parameter::new("guardian", CLType::ByteArray(32)),
+            Parameter::new("new_threshold", CLType::U8),
+        ],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
+    eps.add_entry_point(EntryPoint::new(
+        "remove_guardian",
+        vec![
+            Parameter::new("account", CLType::ByteArray(32)),
+            Parameter::new("guardian", CLType::ByteArray(32)),
+            Parameter::new("new_threshold", CLType::U8),
+        ],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
+    eps.add_entry_point(EntryPoint::new(
+        "replace_guardian",
+        vec![
+            Parameter::new("account", CLType::ByteArray(32)),
+            Parameter::new("old_guardian", CLType::ByteArray(32)),
+            Parameter::new("new_guardian", CLType::ByteArray(32)),
+            Parameter::new("new_threshold", CLType::U8),
         ],
         CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
     ));
@@ -225,7 +485,7 @@ pub extern "C" fn call() {
         "start_recovery",
         vec![
             Parameter::new("account", CLType::ByteArray(32)),
-            Parameter::new("new_key", CLType::PublicKey),
+            Parameter::new("commitment", CLType::ByteArray(32)),
         ],
         CLType::U256, EntryPointAccess::Public, EntryPointType::Called,
     ));
@@ -235,6 +495,31 @@ pub extern "C" fn call() {
         CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
     ));
 
+    eps.add_entry_point(EntryPoint::new(
+        "reveal_key",
+        vec![
+            Parameter::new("id", CLType::U256),
+            Parameter::new("new_key", CLType::PublicKey),
+            Parameter::new("salt", CLType::ByteArray(32)),
+        ],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
+    eps.add_entry_point(EntryPoint::new(
+        "approve_signed",
+        vec![
+            Parameter::new("id", CLType::U256),
+            Parameter::new(
+                "approvals",
+                CLType::List(Box::new(CLType::Tuple2([
+                    Box::new(CLType::PublicKey),
+                    Box::new(CLType::ByteArray(64)),
+                ]))),
+            ),
+        ],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
     eps.add_entry_point(EntryPoint::new(
         "is_approved", vec![Parameter::new("id", CLType::U256)],
         CLType::Bool, EntryPointAccess::Public, EntryPointType::Called,
@@ -245,6 +530,11 @@ pub extern "C" fn call() {
         CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
     ));
 
+    eps.add_entry_point(EntryPoint::new(
+        "cancel_recovery", vec![Parameter::new("id", CLType::U256)],
+        CLType::Unit, EntryPointAccess::Public, EntryPointType::Called,
+    ));
+
     eps.add_entry_point(EntryPoint::new(
         "get_guardians", vec![Parameter::new("account", CLType::ByteArray(32))],
         CLType::List(Box::new(CLType::ByteArray(32))), EntryPointAccess::Public, EntryPointType::Called,